@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     env,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -9,10 +9,13 @@ use std::{
 };
 
 use dotenv::dotenv;
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::process::Command;
 
 use serenity::{
     async_trait,
-    client::{Client, Context, EventHandler},
+    client::{bridge::gateway::ShardManager, Client, Context, EventHandler},
     framework::{
         standard::{
             help_commands,
@@ -26,15 +29,82 @@ use serenity::{
         channel::Message,
         gateway::Ready,
         guild::Guild,
+        id::GuildId,
         misc::Mentionable,
         prelude::{ChannelId, UserId},
     },
-    prelude::Mutex,
+    prelude::{Mutex, RwLock, TypeMap, TypeMapKey},
     Result as SerenityResult,
 };
 use songbird::input::restartable::Restartable;
 use songbird::{Event, EventContext, EventHandler as VoiceEventHandler, SerenityInit, TrackEvent};
 
+/// Display metadata for a track, captured at enqueue time since songbird's
+/// queue itself only tracks playback state, not titles/URLs/requester.
+#[derive(Clone)]
+struct TrackMeta {
+    title: String,
+    url: Option<String>,
+    duration: Option<Duration>,
+    requested_by: String,
+}
+
+struct TrackMetaStore;
+
+impl TypeMapKey for TrackMetaStore {
+    type Value = Arc<Mutex<HashMap<GuildId, VecDeque<TrackMeta>>>>;
+}
+
+/// Per-guild playback volume (songbird's 0.0-2.0 scale), so that tracks
+/// enqueued after a `volume` command inherit the DJ's chosen level.
+struct VolumeStore;
+
+impl TypeMapKey for VolumeStore {
+    type Value = Arc<Mutex<HashMap<GuildId, f32>>>;
+}
+
+/// Guilds Sunny currently holds a voice connection in, so a graceful
+/// shutdown knows which calls to leave without relying on songbird to
+/// enumerate them itself.
+struct ActiveGuilds;
+
+impl TypeMapKey for ActiveGuilds {
+    type Value = Arc<Mutex<HashSet<GuildId>>>;
+}
+
+struct ShardManagerContainer;
+
+impl TypeMapKey for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
+
+async fn guild_volume(data: &Arc<RwLock<TypeMap>>, guild_id: GuildId) -> f32 {
+    let store = data
+        .read()
+        .await
+        .get::<VolumeStore>()
+        .expect("VolumeStore placed in at initialisation")
+        .clone();
+
+    store.lock().await.get(&guild_id).copied().unwrap_or(1.0)
+}
+
+async fn push_track_meta(data: &Arc<RwLock<TypeMap>>, guild_id: GuildId, meta: TrackMeta) {
+    let store = data
+        .read()
+        .await
+        .get::<TrackMetaStore>()
+        .expect("TrackMetaStore placed in at initialisation")
+        .clone();
+
+    store
+        .lock()
+        .await
+        .entry(guild_id)
+        .or_default()
+        .push_back(meta);
+}
+
 struct Handler;
 
 #[async_trait]
@@ -45,14 +115,32 @@ impl EventHandler for Handler {
 }
 
 struct TrackEndNotifier {
+    guild_id: GuildId,
     channel_id: ChannelId,
     http: Arc<Http>,
+    data: Arc<RwLock<TypeMap>>,
 }
 
 #[async_trait]
 impl VoiceEventHandler for TrackEndNotifier {
     async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
         if let EventContext::Track(track_list) = ctx {
+            let store = self
+                .data
+                .read()
+                .await
+                .get::<TrackMetaStore>()
+                .expect("TrackMetaStore placed in at initialisation")
+                .clone();
+
+            let mut store = store.lock().await;
+            if let Some(queue) = store.get_mut(&self.guild_id) {
+                for _ in 0..track_list.len() {
+                    queue.pop_front();
+                }
+            }
+            drop(store);
+
             check_msg(
                 self.channel_id
                     .say(&self.http, format!("Tracks ended: {}", track_list.len()))
@@ -91,6 +179,16 @@ impl VoiceEventHandler for TimeoutHandler {
                     eprintln!("Failed: {:?}", e);
                 }
 
+                let active_guilds = self
+                    .ctx
+                    .data
+                    .read()
+                    .await
+                    .get::<ActiveGuilds>()
+                    .expect("ActiveGuilds placed in at initialisation")
+                    .clone();
+                active_guilds.lock().await.remove(&self.guild.id);
+
                 check_msg(
                     self.channel_id
                         .say(&self.ctx.http, "Left voice due to lack of frens :(((")
@@ -105,6 +203,804 @@ impl VoiceEventHandler for TimeoutHandler {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct YtdlEntry {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YtdlDump {
+    #[serde(default)]
+    entries: Option<Vec<Option<YtdlEntry>>>,
+}
+
+/// Runs `yt-dlp --flat-playlist -J` against `url` and returns the playable
+/// URL for every entry it finds. A plain (non-playlist) URL comes back with
+/// no `entries` array, in which case this returns a single-element vec
+/// containing the original URL unchanged.
+async fn expand_playlist(url: &str) -> Result<Vec<String>, std::io::Error> {
+    let output = Command::new("yt-dlp")
+        .args(&["--flat-playlist", "-J", url])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Ok(vec![url.to_string()]);
+    }
+
+    let dump: YtdlDump = match serde_json::from_slice(&output.stdout) {
+        Ok(dump) => dump,
+        Err(_) => return Ok(vec![url.to_string()]),
+    };
+
+    let entries = match dump.entries {
+        Some(entries) => entries,
+        None => return Ok(vec![url.to_string()]),
+    };
+
+    Ok(entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            entry.url.or_else(|| {
+                entry
+                    .id
+                    .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+            })
+        })
+        .collect())
+}
+
+/// Sources `url` via yt-dlp, enqueues it on `handler_lock`, and records
+/// display metadata for `queue`/`nowplaying` alongside it. Returns `false`
+/// if the URL could not be sourced, e.g. an unavailable playlist entry.
+async fn enqueue_ytdl(
+    handler_lock: &Arc<Mutex<songbird::Call>>,
+    data: &Arc<RwLock<TypeMap>>,
+    guild_id: GuildId,
+    requested_by: &str,
+    url: String,
+) -> bool {
+    let source = match Restartable::ytdl(url.clone(), true).await {
+        Ok(source) => source,
+        Err(why) => {
+            println!("Err starting source for {}: {:?}", url, why);
+            return false;
+        }
+    };
+
+    let input: songbird::input::Input = source.into();
+    let meta = TrackMeta {
+        title: input
+            .metadata
+            .title
+            .clone()
+            .unwrap_or_else(|| "Unknown title".to_string()),
+        url: input.metadata.source_url.clone(),
+        duration: input.metadata.duration,
+        requested_by: requested_by.to_string(),
+    };
+
+    let handle = handler_lock.lock().await.enqueue_source(input);
+    let _ = handle.set_volume(guild_volume(data, guild_id).await);
+    push_track_meta(data, guild_id, meta).await;
+
+    true
+}
+
+/// Formats Symphonia already understands without ffmpeg: mp3, aac, alac and
+/// isomp4/m4a containers.
+fn is_direct_media_url(url: &str) -> bool {
+    let path = url.split(&['?', '#'][..]).next().unwrap_or(url);
+    let ext = path.rsplit('.').next().unwrap_or("").to_lowercase();
+
+    matches!(ext.as_str(), "mp3" | "aac" | "m4a" | "mp4")
+}
+
+/// Maximum time allowed to download a direct media file before giving up.
+const DIRECT_MEDIA_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum size allowed for a direct media download, so a misbehaving or
+/// oversized attachment/link can't buffer unbounded bytes into memory.
+const MAX_DIRECT_MEDIA_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Downloads `url` in full and enqueues it via songbird's Symphonia-backed
+/// decoder, for message attachments and direct media links that don't need
+/// yt-dlp/ffmpeg to resolve. Returns `false` if the download fails, is too
+/// large, or times out; the bytes are not test-decoded before enqueueing.
+async fn enqueue_symphonia(
+    handler_lock: &Arc<Mutex<songbird::Call>>,
+    data: &Arc<RwLock<TypeMap>>,
+    guild_id: GuildId,
+    requested_by: &str,
+    url: String,
+    title: String,
+) -> bool {
+    let client = reqwest::Client::builder()
+        .timeout(DIRECT_MEDIA_DOWNLOAD_TIMEOUT)
+        .build()
+        .expect("Error building reqwest client");
+
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(why) => {
+            println!("Err downloading {}: {:?}", url, why);
+            return false;
+        }
+    };
+
+    if let Some(len) = response.content_length() {
+        if len > MAX_DIRECT_MEDIA_BYTES {
+            println!(
+                "Refusing to download {}: {} bytes exceeds the {} byte cap",
+                url, len, MAX_DIRECT_MEDIA_BYTES
+            );
+            return false;
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(why) => {
+                println!("Err downloading {}: {:?}", url, why);
+                return false;
+            }
+        };
+
+        if bytes.len() as u64 + chunk.len() as u64 > MAX_DIRECT_MEDIA_BYTES {
+            println!(
+                "Refusing to download {}: exceeded the {} byte cap mid-stream",
+                url, MAX_DIRECT_MEDIA_BYTES
+            );
+            return false;
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let metadata = songbird::input::Metadata {
+        title: Some(title.clone()),
+        source_url: Some(url.clone()),
+        ..Default::default()
+    };
+
+    let input = songbird::input::Input::new(
+        true,
+        songbird::input::reader::Reader::Extension(Box::new(std::io::Cursor::new(bytes))),
+        songbird::input::codec::Codec::Auto,
+        songbird::input::Container::Auto,
+        Some(metadata),
+    );
+
+    let meta = TrackMeta {
+        title,
+        url: Some(url),
+        duration: input.metadata.duration,
+        requested_by: requested_by.to_string(),
+    };
+
+    let handle = handler_lock.lock().await.enqueue_source(input);
+    let _ = handle.set_volume(guild_volume(data, guild_id).await);
+    push_track_meta(data, guild_id, meta).await;
+
+    true
+}
+
+/// Abstracts the playback operations so `play`/`skip`/`stop` don't care
+/// whether audio is sourced locally through songbird or offloaded to a
+/// Lavalink node. Exactly one implementation is installed into the bot's
+/// `TypeMap` at startup, chosen by whether `LAVALINK_URL` and
+/// `LAVALINK_PASSWORD` are both configured.
+#[async_trait]
+trait Player: Send + Sync {
+    /// Resolves and enqueues `url` for `guild_id`, returning a human-readable
+    /// summary of what was added. `SongbirdPlayer` enqueues every entry of a
+    /// playlist URL; `LavalinkPlayer` enqueues only its first track. `title_hint` is
+    /// set when `url` is a message attachment, whose real filename is known
+    /// up front rather than having to be sniffed or resolved.
+    async fn enqueue(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        url: String,
+        title_hint: Option<String>,
+        requested_by: String,
+    ) -> Result<String, String>;
+
+    /// Skips the current track, returning the number of tracks left queued.
+    async fn skip(&self, ctx: &Context, guild_id: GuildId) -> Result<usize, String>;
+
+    /// Stops playback and clears the queue.
+    async fn stop(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String>;
+
+    /// Returns the currently playing track's metadata and elapsed playback
+    /// position, or `None` if nothing is playing.
+    async fn now_playing(&self, ctx: &Context, guild_id: GuildId) -> Option<(TrackMeta, Duration)>;
+
+    /// Returns the number of tracks queued, including the current one.
+    async fn queue_len(&self, ctx: &Context, guild_id: GuildId) -> usize;
+
+    /// Pauses the current track.
+    async fn pause(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String>;
+
+    /// Resumes the current track.
+    async fn resume(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String>;
+
+    /// Sets playback volume (songbird's 0.0-2.0 scale) for the current track
+    /// and anything enqueued after it.
+    async fn set_volume(&self, ctx: &Context, guild_id: GuildId, volume: f32)
+        -> Result<(), String>;
+
+    /// Seeks the current track to `target`, only returning once the seek is
+    /// observed to have taken effect (bounded by `SEEK_TIMEOUT`).
+    async fn seek(&self, ctx: &Context, guild_id: GuildId, target: Duration) -> Result<(), String>;
+}
+
+struct PlayerStore;
+
+impl TypeMapKey for PlayerStore {
+    type Value = Arc<dyn Player>;
+}
+
+/// Sources tracks locally through songbird, running the decode on the bot
+/// host itself: web URLs via yt-dlp/ffmpeg, attachments and direct media
+/// links via Symphonia.
+struct SongbirdPlayer;
+
+#[async_trait]
+impl Player for SongbirdPlayer {
+    async fn enqueue(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        url: String,
+        title_hint: Option<String>,
+        requested_by: String,
+    ) -> Result<String, String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel to play in".to_string())?;
+
+        // Attachments and direct media links (mp3/aac/alac/isomp4) are
+        // decoded in-process via Symphonia rather than shelled out to
+        // yt-dlp/ffmpeg, and aren't playlists, so they skip expansion.
+        let title = title_hint.or_else(|| {
+            is_direct_media_url(&url).then(|| {
+                url.rsplit('/')
+                    .next()
+                    .unwrap_or(&url)
+                    .split(&['?', '#'][..])
+                    .next()
+                    .unwrap_or(&url)
+                    .to_string()
+            })
+        });
+
+        if let Some(title) = title {
+            if !enqueue_symphonia(
+                &handler_lock,
+                &ctx.data,
+                guild_id,
+                &requested_by,
+                url,
+                title,
+            )
+            .await
+            {
+                return Err("Error sourcing audio".to_string());
+            }
+
+            let handler = handler_lock.lock().await;
+            return Ok(format!(
+                "Added song to queue: position {}",
+                handler.queue().len()
+            ));
+        }
+
+        let urls = match expand_playlist(&url).await {
+            Ok(urls) if !urls.is_empty() => urls,
+            _ => return Err("Could not find anything to play at that URL".to_string()),
+        };
+
+        if urls.len() == 1 {
+            let url = urls.into_iter().next().unwrap();
+
+            if !enqueue_ytdl(&handler_lock, &ctx.data, guild_id, &requested_by, url).await {
+                return Err("Error sourcing ffmpeg".to_string());
+            }
+
+            let handler = handler_lock.lock().await;
+            return Ok(format!(
+                "Added song to queue: position {}",
+                handler.queue().len()
+            ));
+        }
+
+        let total = urls.len();
+        let mut urls = urls.into_iter();
+
+        // Source and enqueue the first track inline so playback starts
+        // immediately, then hand the rest off to a background task so a
+        // huge playlist doesn't block the command for minutes.
+        let first = urls.next().unwrap();
+        let first_failed =
+            !enqueue_ytdl(&handler_lock, &ctx.data, guild_id, &requested_by, first).await;
+
+        let handler_lock = handler_lock.clone();
+        let data = ctx.data.clone();
+        let http = ctx.http.clone();
+        tokio::spawn(async move {
+            let mut skipped = if first_failed { 1 } else { 0 };
+
+            for url in urls {
+                if !enqueue_ytdl(&handler_lock, &data, guild_id, &requested_by, url).await {
+                    skipped += 1;
+                }
+            }
+
+            if skipped > 0 {
+                check_msg(
+                    channel_id
+                        .say(&http, format!("Skipped {} unavailable track(s)", skipped))
+                        .await,
+                );
+            }
+        });
+
+        Ok(format!("Added {} tracks to queue", total))
+    }
+
+    async fn skip(&self, ctx: &Context, guild_id: GuildId) -> Result<usize, String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel".to_string())?;
+
+        let handler = handler_lock.lock().await;
+        let queue = handler.queue();
+        let _ = queue.skip();
+
+        Ok(queue.len())
+    }
+
+    async fn stop(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel".to_string())?;
+
+        let handler = handler_lock.lock().await;
+        let _ = handler.queue().stop();
+        drop(handler);
+
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+        store.lock().await.remove(&guild_id);
+
+        Ok(())
+    }
+
+    async fn now_playing(&self, ctx: &Context, guild_id: GuildId) -> Option<(TrackMeta, Duration)> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager.get(guild_id)?;
+        let handler = handler_lock.lock().await;
+        let track = handler.queue().current()?;
+        drop(handler);
+
+        let elapsed = track
+            .get_info()
+            .await
+            .map(|state| state.position)
+            .unwrap_or_default();
+
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+        let meta = store
+            .lock()
+            .await
+            .get(&guild_id)
+            .and_then(|queue| queue.front().cloned())?;
+
+        Some((meta, elapsed))
+    }
+
+    async fn queue_len(&self, ctx: &Context, guild_id: GuildId) -> usize {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        match manager.get(guild_id) {
+            Some(handler_lock) => handler_lock.lock().await.queue().len(),
+            None => 0,
+        }
+    }
+
+    async fn pause(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel".to_string())?;
+
+        let handler = handler_lock.lock().await;
+        let track = handler
+            .queue()
+            .current()
+            .ok_or_else(|| "Nothing is playing".to_string())?;
+
+        track
+            .pause()
+            .map_err(|why| format!("Failed to pause: {:?}", why))
+    }
+
+    async fn resume(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel".to_string())?;
+
+        let handler = handler_lock.lock().await;
+        let track = handler
+            .queue()
+            .current()
+            .ok_or_else(|| "Nothing is playing".to_string())?;
+
+        track
+            .play()
+            .map_err(|why| format!("Failed to resume: {:?}", why))
+    }
+
+    async fn set_volume(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        volume: f32,
+    ) -> Result<(), String> {
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<VolumeStore>()
+            .expect("VolumeStore placed in at initialisation")
+            .clone();
+        store.lock().await.insert(guild_id, volume);
+
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        if let Some(handler_lock) = manager.get(guild_id) {
+            let handler = handler_lock.lock().await;
+            if let Some(track) = handler.queue().current() {
+                let _ = track.set_volume(volume);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn seek(&self, ctx: &Context, guild_id: GuildId, target: Duration) -> Result<(), String> {
+        let manager = songbird::get(ctx)
+            .await
+            .expect("Songbird Voice Client placed in at initialisation")
+            .clone();
+
+        let handler_lock = manager
+            .get(guild_id)
+            .ok_or_else(|| "Not in a voice channel".to_string())?;
+
+        let handler = handler_lock.lock().await;
+        let track = handler
+            .queue()
+            .current()
+            .ok_or_else(|| "Nothing is playing".to_string())?;
+        drop(handler);
+
+        track
+            .seek_time(target)
+            .map_err(|why| format!("Failed to seek: {:?}", why))?;
+
+        // `seek_time` only enqueues a `TrackCommand::Seek` for the mixer
+        // thread; poll `get_info` until playback actually reflects the new
+        // position (within `SEEK_TOLERANCE`) instead of reporting success
+        // the instant the command is queued.
+        let deadline = tokio::time::Instant::now() + SEEK_TIMEOUT;
+        loop {
+            tokio::time::sleep(SEEK_POLL_INTERVAL).await;
+
+            if let Ok(state) = track.get_info().await {
+                let delta = state.position.as_secs_f64() - target.as_secs_f64();
+                if delta.abs() <= SEEK_TOLERANCE.as_secs_f64() {
+                    return Ok(());
+                }
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                return Err("Seek timed out while the decoder re-buffered".to_string());
+            }
+        }
+    }
+}
+
+/// Offloads resolution and streaming to a Lavalink node via `lavalink-rs`,
+/// so a multi-guild deployment doesn't have to run ffmpeg/yt-dlp per guild
+/// on the bot host. Selected instead of `SongbirdPlayer` when `LAVALINK_URL`
+/// and `LAVALINK_PASSWORD` are set; the voice gateway connection (`join`/`leave`) is unaffected, only
+/// how tracks are resolved and streamed once connected. `lava` is built
+/// sharing the bot's own `Songbird` instance (see `create_bot`), so it rides
+/// the voice session `join` already establishes instead of needing its own
+/// `voice_state_update`/`voice_server_update` forwarding.
+struct LavalinkPlayer {
+    lava: lavalink_rs::LavalinkClient,
+}
+
+#[async_trait]
+impl Player for LavalinkPlayer {
+    async fn enqueue(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        _channel_id: ChannelId,
+        url: String,
+        _title_hint: Option<String>,
+        requested_by: String,
+    ) -> Result<String, String> {
+        // Lavalink resolves attachments/direct media links itself, same as
+        // any other URL, so `title_hint` isn't needed on this path. Only the
+        // first track the query resolves to is queued, so a playlist URL
+        // adds just its first entry.
+        let query = if url.starts_with("http") {
+            url
+        } else {
+            format!("ytsearch:{}", url)
+        };
+
+        let query_result = self
+            .lava
+            .auto_search_tracks(&query)
+            .await
+            .map_err(|why| format!("Error resolving track: {:?}", why))?;
+
+        let track = query_result
+            .tracks
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Could not find anything to play at that URL".to_string())?;
+
+        self.lava
+            .play(guild_id, track.clone())
+            .queue()
+            .await
+            .map_err(|why| format!("Error queueing track: {:?}", why))?;
+
+        let info = track.info.as_ref();
+        push_track_meta(
+            &ctx.data,
+            guild_id,
+            TrackMeta {
+                title: info
+                    .map(|info| info.title.clone())
+                    .unwrap_or_else(|| "Unknown title".to_string()),
+                url: info.map(|info| info.uri.clone()),
+                duration: info.map(|info| Duration::from_millis(info.length)),
+                requested_by,
+            },
+        )
+        .await;
+
+        Ok("Added song to Lavalink queue".to_string())
+    }
+
+    async fn skip(&self, _ctx: &Context, guild_id: GuildId) -> Result<usize, String> {
+        let remaining = self
+            .lava
+            .skip(guild_id)
+            .await
+            .ok_or_else(|| "Nothing queued on this Lavalink node".to_string())?;
+
+        Ok(remaining.len())
+    }
+
+    async fn stop(&self, ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        self.lava
+            .stop(guild_id)
+            .await
+            .map_err(|why| format!("Failed to stop: {:?}", why))?;
+
+        // `stop` drops every queued track at once, not just the current
+        // one, so (unlike a natural track end) there's no `track_finish`
+        // event per entry to retire metadata with.
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+        store.lock().await.remove(&guild_id);
+
+        Ok(())
+    }
+
+    async fn now_playing(&self, ctx: &Context, guild_id: GuildId) -> Option<(TrackMeta, Duration)> {
+        let node = self.lava.nodes().await.get(&guild_id)?.clone();
+        let position = Duration::from_millis(node.now_playing?.info?.position);
+
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+        let meta = store
+            .lock()
+            .await
+            .get(&guild_id)
+            .and_then(|queue| queue.front().cloned())?;
+
+        Some((meta, position))
+    }
+
+    async fn queue_len(&self, ctx: &Context, guild_id: GuildId) -> usize {
+        let store = ctx
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+
+        store
+            .lock()
+            .await
+            .get(&guild_id)
+            .map(|queue| queue.len())
+            .unwrap_or(0)
+    }
+
+    async fn pause(&self, _ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        self.lava
+            .set_pause(guild_id, true)
+            .await
+            .map_err(|why| format!("Failed to pause: {:?}", why))
+    }
+
+    async fn resume(&self, _ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+        self.lava
+            .set_pause(guild_id, false)
+            .await
+            .map_err(|why| format!("Failed to resume: {:?}", why))
+    }
+
+    async fn set_volume(
+        &self,
+        _ctx: &Context,
+        guild_id: GuildId,
+        volume: f32,
+    ) -> Result<(), String> {
+        // Lavalink's volume op is an integer percentage (0-1000), not
+        // songbird's 0.0-2.0 float scale.
+        let percent = (volume * 100.0).round() as i64;
+
+        self.lava
+            .volume(guild_id, percent)
+            .await
+            .map_err(|why| format!("Failed to set volume: {:?}", why))
+    }
+
+    async fn seek(
+        &self,
+        _ctx: &Context,
+        guild_id: GuildId,
+        target: Duration,
+    ) -> Result<(), String> {
+        self.lava
+            .seek(guild_id, target)
+            .await
+            .map_err(|why| format!("Failed to seek: {:?}", why))?;
+
+        let deadline = tokio::time::Instant::now() + SEEK_TIMEOUT;
+        loop {
+            tokio::time::sleep(SEEK_POLL_INTERVAL).await;
+
+            if let Some(node) = self.lava.nodes().await.get(&guild_id) {
+                if let Some(position) = node
+                    .now_playing
+                    .as_ref()
+                    .and_then(|playing| playing.info.as_ref())
+                    .map(|info| Duration::from_millis(info.position))
+                {
+                    let delta = position.as_secs_f64() - target.as_secs_f64();
+                    if delta.abs() <= SEEK_TOLERANCE.as_secs_f64() {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                return Err("Seek timed out while the decoder re-buffered".to_string());
+            }
+        }
+    }
+}
+
+/// Mirrors `TrackEndNotifier` for the Lavalink backend: retires queued
+/// metadata as Lavalink reports tracks finishing, since the node (not
+/// songbird) owns the actual playback queue in this mode.
+struct LavalinkTrackEndNotifier {
+    data: Arc<RwLock<TypeMap>>,
+}
+
+#[async_trait]
+impl lavalink_rs::LavalinkEventHandler for LavalinkTrackEndNotifier {
+    async fn track_finish(
+        &self,
+        _client: lavalink_rs::LavalinkClient,
+        event: lavalink_rs::model::TrackFinish,
+    ) {
+        let guild_id = GuildId(event.guild_id.0);
+
+        let store = self
+            .data
+            .read()
+            .await
+            .get::<TrackMetaStore>()
+            .expect("TrackMetaStore placed in at initialisation")
+            .clone();
+
+        if let Some(queue) = store.lock().await.get_mut(&guild_id) {
+            queue.pop_front();
+        }
+    }
+}
+
 fn check_alone(guild: &Guild, channel_id: ChannelId, bot_id: UserId) -> bool {
     !guild.voice_states.values().any(|vs| match vs.channel_id {
         Some(c_id) => channel_id.0 == c_id.0 && vs.user_id.0 != bot_id.0,
@@ -113,7 +1009,9 @@ fn check_alone(guild: &Guild, channel_id: ChannelId, bot_id: UserId) -> bool {
 }
 
 #[group]
-#[commands(join, leave, play, ping, skip, stop)]
+#[commands(
+    join, leave, play, ping, skip, seek, pause, resume, volume, stop, nowplaying, queue
+)]
 struct General;
 
 #[help]
@@ -164,6 +1062,15 @@ async fn join(ctx: &Context, msg: &Message) -> CommandResult {
                 .await,
         );
 
+        let active_guilds = ctx
+            .data
+            .read()
+            .await
+            .get::<ActiveGuilds>()
+            .expect("ActiveGuilds placed in at initialisation")
+            .clone();
+        active_guilds.lock().await.insert(guild_id);
+
         let channel_id = msg.channel_id;
         let send_http = ctx.http.clone();
         let mut handle = handle_lock.lock().await;
@@ -171,8 +1078,10 @@ async fn join(ctx: &Context, msg: &Message) -> CommandResult {
         handle.add_global_event(
             Event::Track(TrackEvent::End),
             TrackEndNotifier {
+                guild_id,
                 channel_id,
                 http: send_http,
+                data: ctx.data.clone(),
             },
         );
 
@@ -222,6 +1131,20 @@ async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
     let has_handler = manager.get(guild_id).is_some();
 
     if has_handler {
+        let player = ctx
+            .data
+            .read()
+            .await
+            .get::<PlayerStore>()
+            .expect("PlayerStore placed in at initialisation")
+            .clone();
+
+        // Tear down the player session (Lavalink node or songbird queue)
+        // and retire its TrackMetaStore entry the same way `stop` does, so
+        // a later `nowplaying`/`queue` before a fresh `play` doesn't show
+        // stale data from this session.
+        let _ = player.stop(ctx, guild_id).await;
+
         if let Err(e) = manager.remove(guild_id).await {
             check_msg(
                 msg.channel_id
@@ -230,6 +1153,15 @@ async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
             );
         }
 
+        let active_guilds = ctx
+            .data
+            .read()
+            .await
+            .get::<ActiveGuilds>()
+            .expect("ActiveGuilds placed in at initialisation")
+            .clone();
+        active_guilds.lock().await.remove(&guild_id);
+
         check_msg(msg.channel_id.say(&ctx.http, "Left voice").await);
     } else {
         check_msg(msg.reply(ctx, "Not in a voice channel").await);
@@ -245,19 +1177,52 @@ async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
 #[usage("<url>")]
 #[example("https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
 /// While Sunny is in a voice channel, you may run the play command so that she
-/// can start streaming the given video URL.
+/// can start streaming the given video URL. If the URL points at a playlist,
+/// every track in it is enqueued (only the first one when Sunny runs on the
+/// Lavalink backend). A direct audio/video file (or message
+/// attachment) is played straight from its bytes instead of through yt-dlp.
 async fn play(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let url = match args.single::<String>() {
-        Ok(url) => url,
-        Err(_) => {
-            check_msg(
-                msg.channel_id
-                    .say(&ctx.http, "Must provide a URL to a video or audio")
-                    .await,
-            );
+    let (url, title_hint) = match args.single::<String>() {
+        Ok(url) => (url, None),
+        Err(_) => match msg.attachments.first() {
+            Some(attachment) => {
+                let is_audio_or_video = attachment
+                    .content_type
+                    .as_deref()
+                    .map(|content_type| {
+                        content_type.starts_with("audio") || content_type.starts_with("video")
+                    })
+                    .unwrap_or(false)
+                    || is_direct_media_url(&attachment.filename);
 
-            return Ok(());
-        }
+                if !is_audio_or_video {
+                    check_msg(
+                        msg.channel_id
+                            .say(
+                                &ctx.http,
+                                "That attachment doesn't look like a supported audio/video file",
+                            )
+                            .await,
+                    );
+
+                    return Ok(());
+                }
+
+                (attachment.url.clone(), Some(attachment.filename.clone()))
+            }
+            None => {
+                check_msg(
+                    msg.channel_id
+                        .say(
+                            &ctx.http,
+                            "Must provide a URL or attach an audio/video file",
+                        )
+                        .await,
+                );
+
+                return Ok(());
+            }
+        },
     };
 
     if !url.starts_with("http") {
@@ -270,78 +1235,244 @@ async fn play(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
         return Ok(());
     }
 
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+    let guild_id = msg.guild(&ctx.cache).await.unwrap().id;
+    let requested_by = msg.author.name.clone();
 
-    let manager = songbird::get(ctx)
+    let player = ctx
+        .data
+        .read()
         .await
-        .expect("Songbird Voice Client placed in at initialisation")
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
         .clone();
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let mut handler = handler_lock.lock().await;
+    match player
+        .enqueue(ctx, guild_id, msg.channel_id, url, title_hint, requested_by)
+        .await
+    {
+        Ok(summary) => check_msg(msg.channel_id.say(&ctx.http, summary).await),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
+    }
 
-        let source = match Restartable::ytdl(url, true).await {
-            Ok(source) => source,
-            Err(why) => {
-                println!("Err starting source {:?}", why);
-                check_msg(msg.channel_id.say(&ctx.http, "Error sourcing ffmpeg").await);
+    Ok(())
+}
 
-                return Ok(());
-            }
-        };
+#[command]
+#[only_in(guilds)]
+/// Skips the currently playing song and moves to the next song in the queue.
+async fn skip(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
 
-        handler.enqueue_source(source.into());
-        check_msg(
-            msg.channel_id
-                .say(
-                    &ctx.http,
-                    format!("Added song to queue: position {}", handler.queue().len()),
-                )
-                .await,
-        );
-    } else {
-        check_msg(
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    match player.skip(ctx, guild_id).await {
+        Ok(remaining) => check_msg(
             msg.channel_id
-                .say(&ctx.http, "Not in a voice channel to play in")
+                .say(&ctx.http, format!("Song skipped: {} in queue.", remaining))
                 .await,
-        );
+        ),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
     }
 
     Ok(())
 }
 
+/// Duration a seek is allowed to take before `seek` gives up and reports a
+/// timeout; a ytdl `Restartable` source can stall for several seconds while
+/// the decoder re-buffers around the new position.
+const SEEK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a `Player::seek` implementation polls for the seek to have
+/// landed.
+const SEEK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How close the reported playback position has to land to the requested
+/// seek target to count as having taken effect.
+const SEEK_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Parses a `mm:ss` or plain-seconds timestamp into a `Duration`.
+fn parse_timestamp(raw: &str) -> Option<Duration> {
+    match raw.split_once(':') {
+        Some((mins, secs)) => {
+            let mins: u64 = mins.parse().ok()?;
+            let secs: u64 = secs.parse().ok()?;
+
+            if secs >= 60 {
+                return None;
+            }
+
+            Some(Duration::from_secs(mins * 60 + secs))
+        }
+        None => Some(Duration::from_secs(raw.parse().ok()?)),
+    }
+}
+
 #[command]
 #[only_in(guilds)]
-/// Skips the currently playing song and moves to the next song in the queue.
-async fn skip(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+#[usage("<mm:ss|seconds>")]
+#[example("1:30")]
+/// Seeks the currently playing track to the given timestamp.
+async fn seek(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let timestamp = match args.single::<String>() {
+        Ok(timestamp) => timestamp,
+        Err(_) => {
+            check_msg(
+                msg.channel_id
+                    .say(&ctx.http, "Must provide a timestamp, e.g. `1:30` or `90`")
+                    .await,
+            );
 
-    let manager = songbird::get(ctx)
+            return Ok(());
+        }
+    };
+
+    let target = match parse_timestamp(&timestamp) {
+        Some(target) => target,
+        None => {
+            check_msg(
+                msg.channel_id
+                    .say(&ctx.http, "Could not parse that timestamp")
+                    .await,
+            );
+
+            return Ok(());
+        }
+    };
+
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
         .await
-        .expect("Songbird Voice Client placed in at initialisation")
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
         .clone();
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        let queue = handler.queue();
-        let _ = queue.skip();
+    let known_duration = player
+        .now_playing(ctx, guild_id)
+        .await
+        .and_then(|(meta, _)| meta.duration);
 
-        check_msg(
-            msg.channel_id
-                .say(
-                    &ctx.http,
-                    format!("Song skipped: {} in queue.", queue.len()),
-                )
-                .await,
-        );
-    } else {
-        check_msg(
+    if let Some(duration) = known_duration {
+        if target > duration {
+            check_msg(
+                msg.channel_id
+                    .say(&ctx.http, "That's past the end of the track")
+                    .await,
+            );
+
+            return Ok(());
+        }
+    }
+
+    let mut ack = match msg.channel_id.say(&ctx.http, "Seeking...").await {
+        Ok(ack) => ack,
+        Err(why) => {
+            println!("Error sending message: {:?}", why);
+            return Ok(());
+        }
+    };
+
+    let response = match player.seek(ctx, guild_id, target).await {
+        Ok(()) => format!("Seeked to {}", format_position(target, known_duration)),
+        Err(why) => why,
+    };
+
+    let _ = ack.edit(ctx, |m| m.content(response)).await;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+/// Pauses the currently playing track.
+async fn pause(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    match player.pause(ctx, guild_id).await {
+        Ok(()) => check_msg(msg.channel_id.say(&ctx.http, "Paused").await),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+/// Resumes the currently paused track.
+async fn resume(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    match player.resume(ctx, guild_id).await {
+        Ok(()) => check_msg(msg.channel_id.say(&ctx.http, "Resumed").await),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[usage("<0-200>")]
+#[example("80")]
+/// Sets playback volume as a percentage; 100 is the source's original
+/// volume. Applies to the current track and is inherited by future ones.
+async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let percent = match args.single::<i64>() {
+        Ok(percent) => percent,
+        Err(_) => {
+            check_msg(
+                msg.channel_id
+                    .say(&ctx.http, "Must provide a volume percentage, e.g. `80`")
+                    .await,
+            );
+
+            return Ok(());
+        }
+    };
+
+    let percent = percent.clamp(0, 200) as u32;
+    let volume = percent as f32 / 100.0;
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    match player.set_volume(ctx, guild_id, volume).await {
+        Ok(()) => check_msg(
             msg.channel_id
-                .say(&ctx.http, "Not in a voice channel")
+                .say(&ctx.http, format!("Volume set to {}%", percent))
                 .await,
-        );
+        ),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
     }
 
     Ok(())
@@ -351,28 +1482,145 @@ async fn skip(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
 #[only_in(guilds)]
 /// Stops playing the current song and clears the current song queue.
 async fn stop(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+    let guild_id = msg.guild_id.unwrap();
 
-    let manager = songbird::get(ctx)
+    let player = ctx
+        .data
+        .read()
         .await
-        .expect("Songbird Voice Client placed in at initialisation")
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
         .clone();
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        let queue = handler.queue();
-        let _ = queue.stop();
+    match player.stop(ctx, guild_id).await {
+        Ok(()) => check_msg(msg.channel_id.say(&ctx.http, "Queue cleared.").await),
+        Err(why) => check_msg(msg.channel_id.say(&ctx.http, why).await),
+    }
 
-        check_msg(msg.channel_id.say(&ctx.http, "Queue cleared.").await);
-    } else {
+    Ok(())
+}
+
+/// Formats a playback position, and optionally a known total duration, as
+/// `mm:ss` (or `mm:ss / mm:ss`).
+fn format_position(elapsed: Duration, total: Option<Duration>) -> String {
+    fn mmss(d: Duration) -> String {
+        let secs = d.as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+
+    match total {
+        Some(total) => format!("{} / {}", mmss(elapsed), mmss(total)),
+        None => mmss(elapsed),
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+/// Shows the track currently playing, if any.
+async fn nowplaying(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    let (meta, elapsed) = match player.now_playing(ctx, guild_id).await {
+        Some(state) => state,
+        None => {
+            check_msg(msg.channel_id.say(&ctx.http, "Nothing is playing").await);
+
+            return Ok(());
+        }
+    };
+
+    check_msg(
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| {
+                    e.title(meta.title.as_str());
+                    e.field("Duration", format_position(elapsed, meta.duration), false);
+
+                    if let Some(url) = meta.url.clone() {
+                        e.url(url);
+                    }
+
+                    e.footer(|f| f.text(format!("Requested by {}", meta.requested_by)));
+
+                    e
+                })
+            })
+            .await,
+    );
+
+    Ok(())
+}
+
+const QUEUE_PAGE_SIZE: usize = 20;
+
+#[command]
+#[only_in(guilds)]
+/// Lists the tracks queued up after the one currently playing.
+async fn queue(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let player = ctx
+        .data
+        .read()
+        .await
+        .get::<PlayerStore>()
+        .expect("PlayerStore placed in at initialisation")
+        .clone();
+
+    let queue_len = player.queue_len(ctx, guild_id).await;
+
+    if queue_len <= 1 {
         check_msg(
             msg.channel_id
-                .say(&ctx.http, "Not in a voice channel")
+                .say(&ctx.http, "Nothing queued up next")
                 .await,
         );
+
+        return Ok(());
     }
 
+    let store = ctx
+        .data
+        .read()
+        .await
+        .get::<TrackMetaStore>()
+        .expect("TrackMetaStore placed in at initialisation")
+        .clone();
+    let metas = store.lock().await.get(&guild_id).cloned();
+
+    let upcoming: Vec<String> = (1..queue_len)
+        .take(QUEUE_PAGE_SIZE)
+        .map(|i| {
+            let title = metas
+                .as_ref()
+                .and_then(|m| m.get(i))
+                .map_or("Unknown title", |m| &m.title);
+
+            format!("{}. {}", i, title)
+        })
+        .collect();
+
+    let mut description = upcoming.join("\n");
+    if queue_len - 1 > QUEUE_PAGE_SIZE {
+        description.push_str(&format!("\n…and {} more", queue_len - 1 - QUEUE_PAGE_SIZE));
+    }
+
+    check_msg(
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| e.title("Up next").description(description))
+            })
+            .await,
+    );
+
     Ok(())
 }
 
@@ -400,17 +1648,83 @@ pub async fn create_bot() {
         .group(&GENERAL_GROUP)
         .help(&HELP);
 
+    let songbird = songbird::Songbird::serenity();
+
     let mut client = Client::builder(&token)
         .event_handler(Handler)
         .framework(framework)
-        .register_songbird()
+        .register_songbird_with(songbird.clone())
         .await
         .expect("Error creating client");
 
+    let player: Arc<dyn Player> = match (env::var("LAVALINK_URL"), env::var("LAVALINK_PASSWORD")) {
+        (Ok(host), Ok(password)) => {
+            let bot_id = Http::new_with_token(&token)
+                .get_current_user()
+                .await
+                .expect("Could not fetch bot user id for Lavalink")
+                .id;
+
+            let lava = lavalink_rs::LavalinkClient::builder(bot_id.0)
+                .set_host(host)
+                .set_password(password)
+                // Share the bot's own Songbird instance so Lavalink can
+                // read the voice session `join` establishes (guild ->
+                // endpoint/token/session id) instead of needing its own
+                // voice_state_update/voice_server_update forwarding.
+                .set_songbird(songbird.clone())
+                .build(LavalinkTrackEndNotifier {
+                    data: client.data.clone(),
+                })
+                .await
+                .expect("Error connecting to Lavalink");
+
+            Arc::new(LavalinkPlayer { lava })
+        }
+        _ => Arc::new(SongbirdPlayer),
+    };
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<TrackMetaStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<VolumeStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<PlayerStore>(player);
+        data.insert::<ActiveGuilds>(Arc::new(Mutex::new(HashSet::new())));
+        data.insert::<ShardManagerContainer>(client.shard_manager.clone());
+    }
+
+    let shutdown_data = client.data.clone();
+
     tokio::spawn(async move {
         let _ = client
             .start()
             .await
             .map_err(|why| println!("Client ended: {:?}", why));
     });
+
+    tokio::spawn(async move {
+        let _ = tokio::signal::ctrl_c().await;
+        println!("Shutting down, leaving active voice calls...");
+
+        let active_guilds = shutdown_data
+            .read()
+            .await
+            .get::<ActiveGuilds>()
+            .expect("ActiveGuilds placed in at initialisation")
+            .clone();
+
+        for guild_id in active_guilds.lock().await.drain() {
+            if let Err(e) = songbird.remove(guild_id).await {
+                eprintln!("Failed to leave {}: {:?}", guild_id, e);
+            }
+        }
+
+        let shard_manager = shutdown_data
+            .read()
+            .await
+            .get::<ShardManagerContainer>()
+            .expect("ShardManagerContainer placed in at initialisation")
+            .clone();
+        shard_manager.lock().await.shutdown_all().await;
+    });
 }